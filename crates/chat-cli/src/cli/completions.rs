@@ -1,6 +0,0 @@
-//! Shell completions support (fish, zsh, bash, etc).
-//!
-//! Generation logic is implemented in `cli/mod.rs` via the `Completions` RootSubcommand
-//! variant. This module exists as a placeholder for potential future expansion
-//! (e.g., cached generation, custom installers, rich text previews).
-#![allow(dead_code)]
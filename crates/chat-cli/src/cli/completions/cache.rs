@@ -0,0 +1,134 @@
+//! Cached completion generation, keyed on CLI version and a digest of the clap
+//! command tree, so regenerating a script (e.g. on every shell startup via
+//! `q completions <shell> --cached`) is near-instant once it's been generated once.
+//!
+//! The cache key intentionally does *not* trust "nothing changed since last time" -
+//! it's derived from the actual command tree, so a binary upgrade or a feature flag
+//! that adds/removes subcommands naturally produces a new key and a fresh
+//! regeneration instead of serving a stale script. The clap version is not part of
+//! the key, so a clap upgrade alone does not change it.
+
+use std::fs;
+use std::path::PathBuf;
+
+use clap::Command;
+use clap_complete::Shell;
+use eyre::{
+    Result,
+    eyre,
+};
+
+use super::install::generate_script;
+
+/// Directory generated scripts are cached under.
+fn cache_dir() -> Result<PathBuf> {
+    let base = dirs::cache_dir().ok_or_else(|| eyre!("could not determine cache directory"))?;
+    Ok(base.join("q").join("completions"))
+}
+
+/// Feeds a stable (not dependent on hash-map randomization) digest of the clap
+/// command tree into `hasher`: subcommand names, argument ids, long/short flag
+/// names, and value hints. Two `Command`s with the same shape hash the same; help
+/// text, possible values and aliases are not part of the digest, so a change to
+/// only those keeps the same hash.
+fn hash_command(cmd: &Command, hasher: &mut Fnv1a) {
+    hasher.write(cmd.get_name().as_bytes());
+
+    for arg in cmd.get_arguments() {
+        hasher.write(arg.get_id().as_str().as_bytes());
+        if let Some(long) = arg.get_long() {
+            hasher.write(long.as_bytes());
+        }
+        if let Some(short) = arg.get_short() {
+            hasher.write(&[short as u8]);
+        }
+        hasher.write(format!("{:?}", arg.get_value_hint()).as_bytes());
+    }
+
+    // Subcommands are hashed in a fixed (name-sorted) order so the digest doesn't
+    // depend on clap's internal registration order.
+    let mut subcommands: Vec<&Command> = cmd.get_subcommands().collect();
+    subcommands.sort_by_key(|s| s.get_name());
+    for sub in subcommands {
+        hash_command(sub, hasher);
+    }
+}
+
+/// Minimal FNV-1a implementation so the digest is stable across processes, unlike
+/// `std::hash::DefaultHasher`, whose algorithm and seeding are not part of its API
+/// contract.
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= b as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// The fully-qualified key identifying one cached script: shell, crate version, the
+/// command-tree digest, and a fingerprint of whatever enabled feature flags the
+/// caller considers relevant (so two builds with different subcommands compiled in
+/// never share a cache entry even if their visible command trees happened to
+/// collide).
+fn cache_file_name(shell: Shell, crate_version: &str, cmd: &Command, enabled_features: &[&str]) -> String {
+    let mut digest = Fnv1a::default();
+    hash_command(cmd, &mut digest);
+    for feature in enabled_features {
+        digest.write(feature.as_bytes());
+    }
+    format!("{shell}-{crate_version}-{:016x}", digest.finish())
+}
+
+/// Returns the cached script for `shell` if present, regenerating (and caching) it
+/// otherwise.
+///
+/// Regeneration is written atomically (write to a temp file, then rename into
+/// place) so a concurrent reader never observes a partially written script.
+pub fn get_or_generate(
+    cmd: &mut Command,
+    bin_name: &str,
+    shell: Shell,
+    crate_version: &str,
+    enabled_features: &[&str],
+) -> Result<Vec<u8>> {
+    let dir = cache_dir()?;
+    let file_name = cache_file_name(shell, crate_version, cmd, enabled_features);
+    let path = dir.join(&file_name);
+
+    if let Ok(cached) = fs::read(&path) {
+        return Ok(cached);
+    }
+
+    let script = generate_script(cmd, bin_name, shell);
+
+    fs::create_dir_all(&dir)?;
+    let tmp_path = dir.join(format!("{file_name}.tmp-{}", std::process::id()));
+    fs::write(&tmp_path, &script)?;
+    fs::rename(&tmp_path, &path)?;
+
+    Ok(script)
+}
+
+/// Removes every cached completion script. Used by `q completions clear-cache` and
+/// after anything that could invalidate entries the digest doesn't already cover.
+pub fn clear_cache() -> Result<()> {
+    let dir = cache_dir()?;
+    match fs::remove_dir_all(&dir) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err.into()),
+    }
+}
@@ -0,0 +1,395 @@
+//! Dynamic completion: resolving candidates that can't be baked into a static
+//! `clap_complete` script because they depend on live state (profiles, config keys,
+//! remembered prompts, matching file paths).
+//!
+//! The entry point is [`complete_dynamic`], which is what the hidden `q _complete`
+//! (zsh/fish) and `q _complete_bash` (bash, invoked via `complete -C`) subcommands
+//! call. Each shell's completion stub (see [`shell_stub`]) re-invokes one of those
+//! on every Tab with the current command line and cursor position, and prints back
+//! whatever candidates we return here.
+
+use std::ffi::OsString;
+use std::path::Path;
+
+use clap::{
+    Command,
+    ValueHint,
+};
+
+/// A single completion candidate.
+///
+/// `description` is shown inline by shells that support it (zsh, fish); bash
+/// completion ignores it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub value: String,
+    pub description: Option<String>,
+}
+
+impl Completion {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self {
+            value: value.into(),
+            description: None,
+        }
+    }
+
+    pub fn with_description(value: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            value: value.into(),
+            description: Some(description.into()),
+        }
+    }
+}
+
+/// What kind of value is expected at the cursor position, determined by walking the
+/// clap command tree. This drives which dynamic resolver (if any) we call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ExpectedValue {
+    /// A subcommand or flag name reachable from `cmd` - static, handled the same way
+    /// `clap_complete`'s own scripts already do.
+    Grammar,
+    /// A file or directory path argument (`ValueHint::AnyPath`/`FilePath`/`DirPath`).
+    Path { dirs_only: bool },
+    /// A named dynamic value kind, e.g. `profile`, `config-key`, `recent-prompt`.
+    /// Populated from an argument's `id()` by convention (see [`classify`]).
+    Named(&'static str),
+}
+
+/// Walks `cmd` to the subcommand active at `args[..cursor]` and classifies what kind
+/// of value the word at `cursor` should complete to, returning that subcommand
+/// alongside the classification so [`complete_dynamic`] can enumerate its grammar
+/// (subcommands/flags) without re-walking the tree.
+fn classify(cmd: &Command, args: &[OsString], cursor: usize) -> (Command, ExpectedValue) {
+    let mut current = cmd.clone();
+    // Start at 1: `args[0]` is the binary name (`q`), never a subcommand.
+    let mut i = 1;
+
+    // Descend into subcommands for every preceding word that names one, so
+    // `q profile use <TAB>` classifies against the `profile use` subcommand rather
+    // than the root command.
+    while i < cursor {
+        let word = args[i].to_string_lossy();
+        match current.find_subcommand(word.as_ref()) {
+            Some(sub) => {
+                current = sub.clone();
+                i += 1;
+            },
+            None => break,
+        }
+    }
+
+    // The word immediately before the cursor tells us whether we're completing a
+    // flag's value (`--profile <TAB>`), in which case we classify against that
+    // flag rather than the partial word itself. Switch flags (`--fuzzy`) take no
+    // value, so they fall through to the checks below instead.
+    if cursor > 0 {
+        let prev = args[cursor - 1].to_string_lossy();
+        if let Some(name) = prev.strip_prefix("--") {
+            if let Some(arg) = current.get_arguments().find(|a| a.get_long() == Some(name)) {
+                if takes_value(arg) {
+                    let expected = classify_value_hint(&arg.get_value_hint(), arg.get_id().as_str());
+                    return (current, expected);
+                }
+            }
+        }
+    }
+
+    // The word being completed is itself a flag name (`--pro<TAB>`), not a value -
+    // leave it to the grammar branch, which enumerates flags as well as
+    // subcommands.
+    let partial = args.get(cursor).map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    if partial.starts_with('-') {
+        return (current, ExpectedValue::Grammar);
+    }
+
+    if let Some(positional) = current.get_positionals().next() {
+        let expected = classify_value_hint(&positional.get_value_hint(), positional.get_id().as_str());
+        return (current, expected);
+    }
+
+    (current, ExpectedValue::Grammar)
+}
+
+/// Whether `arg` consumes a value (`--profile NAME`) as opposed to being a bare
+/// switch (`--fuzzy`).
+fn takes_value(arg: &clap::Arg) -> bool {
+    arg.get_num_args().is_some_and(|n| n.max_values() > 0)
+}
+
+fn classify_value_hint(hint: &ValueHint, arg_id: &str) -> ExpectedValue {
+    match hint {
+        ValueHint::FilePath | ValueHint::AnyPath => ExpectedValue::Path { dirs_only: false },
+        ValueHint::DirPath => ExpectedValue::Path { dirs_only: true },
+        _ => match arg_id {
+            "profile" | "config-key" | "recent-prompt" => ExpectedValue::Named(intern_arg_id(arg_id)),
+            _ => ExpectedValue::Grammar,
+        },
+    }
+}
+
+/// `arg_id` values we recognize as dynamic value kinds; interning avoids threading a
+/// `String` through [`ExpectedValue::Named`].
+fn intern_arg_id(arg_id: &str) -> &'static str {
+    match arg_id {
+        "profile" => "profile",
+        "config-key" => "config-key",
+        "recent-prompt" => "recent-prompt",
+        _ => unreachable!("classify_value_hint only calls this for known ids"),
+    }
+}
+
+/// Resolves the dynamic candidates for `q _complete -- <args> <cursor>`.
+///
+/// `args` is the full argv of the command line being completed (including `q`
+/// itself); `cursor` is the index of the word the user is currently completing.
+pub fn complete_dynamic(cmd: &Command, args: &[OsString], cursor: usize) -> Vec<Completion> {
+    let partial = args.get(cursor).map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let (current, expected) = classify(cmd, args, cursor);
+
+    match expected {
+        ExpectedValue::Grammar => complete_grammar(&current, &partial),
+        ExpectedValue::Path { dirs_only } => complete_path(&partial, dirs_only),
+        ExpectedValue::Named("profile") => complete_profiles(&partial),
+        ExpectedValue::Named("config-key") => complete_config_keys(&partial),
+        ExpectedValue::Named("recent-prompt") => complete_recent_prompts(&partial),
+        ExpectedValue::Named(_) => Vec::new(),
+    }
+}
+
+/// Enumerates `cmd`'s own subcommand names and long flag names matching `partial` -
+/// the subcommand and long-flag candidates a static `clap_complete` script would
+/// offer; short flags are not listed. The dynamic
+/// stub must cover this itself since, once installed, it fully replaces the
+/// shell's static completion rather than supplementing it.
+fn complete_grammar(cmd: &Command, partial: &str) -> Vec<Completion> {
+    let mut out = Vec::new();
+
+    for sub in cmd.get_subcommands() {
+        let name = sub.get_name();
+        if name.starts_with(partial) {
+            out.push(completion_with_optional_description(
+                name.to_string(),
+                sub.get_about().map(|a| a.to_string()),
+            ));
+        }
+    }
+
+    for arg in cmd.get_arguments() {
+        if arg.is_positional() {
+            continue;
+        }
+        if let Some(long) = arg.get_long() {
+            let value = format!("--{long}");
+            if value.starts_with(partial) {
+                out.push(completion_with_optional_description(
+                    value,
+                    arg.get_help().map(|h| h.to_string()),
+                ));
+            }
+        }
+    }
+
+    out.sort_by(|a, b| a.value.cmp(&b.value));
+    out
+}
+
+fn completion_with_optional_description(value: String, description: Option<String>) -> Completion {
+    match description {
+        Some(description) => Completion::with_description(value, description),
+        None => Completion::new(value),
+    }
+}
+
+/// Lists filesystem entries matching `partial`, the same way a shell's builtin path
+/// completion would, but routed through us so it composes with the fzf trigger.
+fn complete_path(partial: &str, dirs_only: bool) -> Vec<Completion> {
+    let (dir, prefix) = match partial.rfind('/') {
+        Some(idx) => (Path::new(&partial[..=idx]), &partial[idx + 1..]),
+        None => (Path::new("."), partial),
+    };
+
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut out = Vec::new();
+    for entry in entries.flatten() {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if dirs_only && !file_type.is_dir() {
+            continue;
+        }
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if !name.starts_with(prefix) {
+            continue;
+        }
+        let mut value = if dir == Path::new(".") {
+            name.into_owned()
+        } else {
+            format!("{}{name}", dir.display())
+        };
+        if file_type.is_dir() {
+            value.push('/');
+        }
+        out.push(Completion::new(value));
+    }
+    out.sort_by(|a, b| a.value.cmp(&b.value));
+    out
+}
+
+fn complete_profiles(partial: &str) -> Vec<Completion> {
+    list_profiles()
+        .into_iter()
+        .filter(|p| p.starts_with(partial))
+        .map(Completion::new)
+        .collect()
+}
+
+/// Reads profile names out of `~/.aws/config`, the conventional store for named
+/// profiles on this CLI's target platforms. Parses `[profile NAME]` section
+/// headers (and bare `[default]`) the same way the AWS CLI's own config parser
+/// does; missing or unreadable files just yield no profiles rather than an error,
+/// since completion has nothing better to fall back to anyway.
+fn list_profiles() -> Vec<String> {
+    let Some(home) = dirs::home_dir() else {
+        return Vec::new();
+    };
+    let Ok(contents) = std::fs::read_to_string(home.join(".aws/config")) else {
+        return Vec::new();
+    };
+
+    let mut profiles = Vec::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        let Some(header) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) else {
+            continue;
+        };
+        match header.strip_prefix("profile ") {
+            Some(name) => profiles.push(name.trim().to_string()),
+            None if header == "default" => profiles.push("default".to_string()),
+            None => {},
+        }
+    }
+    profiles
+}
+
+fn complete_config_keys(partial: &str) -> Vec<Completion> {
+    list_config_keys()
+        .into_iter()
+        .filter(|(key, _)| key.starts_with(partial))
+        .map(|(key, desc)| Completion::with_description(key, desc))
+        .collect()
+}
+
+/// Reads configured setting keys out of `~/.config/q/settings.json`, pairing each
+/// with its current value (rendered compactly) as the completion's description so
+/// the user can see what they'd be overwriting. No settings file yet means no
+/// candidates, not an error.
+fn list_config_keys() -> Vec<(String, String)> {
+    let Some(home) = dirs::home_dir() else {
+        return Vec::new();
+    };
+    let Ok(contents) = std::fs::read_to_string(home.join(".config/q/settings.json")) else {
+        return Vec::new();
+    };
+    let Ok(serde_json::Value::Object(settings)) = serde_json::from_str(&contents) else {
+        return Vec::new();
+    };
+
+    settings.into_iter().map(|(key, value)| (key, value.to_string())).collect()
+}
+
+fn complete_recent_prompts(partial: &str) -> Vec<Completion> {
+    list_recent_prompts()
+        .into_iter()
+        .filter(|p| p.starts_with(partial))
+        .map(Completion::new)
+        .collect()
+}
+
+/// Reads remembered prompts from `~/.local/share/q/history`, one prompt per line,
+/// oldest first (the convention a simple append-only history file follows).
+/// Candidates are returned most-recent-first with duplicates collapsed to their
+/// most recent occurrence, same as shell history search.
+fn list_recent_prompts() -> Vec<String> {
+    let Some(home) = dirs::home_dir() else {
+        return Vec::new();
+    };
+    let Ok(contents) = std::fs::read_to_string(home.join(".local/share/q/history")) else {
+        return Vec::new();
+    };
+
+    let mut seen = std::collections::HashSet::new();
+    let mut prompts = Vec::new();
+    for line in contents.lines().rev() {
+        if line.is_empty() {
+            continue;
+        }
+        if seen.insert(line.to_string()) {
+            prompts.push(line.to_string());
+        }
+    }
+    prompts
+}
+
+/// Renders candidates as lines shells can consume.
+///
+/// Plain mode prints one value per line (what `COMPREPLY`/`compadd` expect). With
+/// `with_descriptions`, each line is `value\tdescription` instead, which is what the
+/// fzf-backed stub in [`super::fuzzy`] expects so it can show the description as a
+/// separate column while still completing on the value alone.
+pub fn format_candidates(candidates: &[Completion], with_descriptions: bool) -> String {
+    let mut out = String::new();
+    for candidate in candidates {
+        out.push_str(&candidate.value);
+        if with_descriptions {
+            out.push('\t');
+            out.push_str(candidate.description.as_deref().unwrap_or(""));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Which shell a dynamic completion stub is being generated for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DynamicShell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// Generates the shell stub that drives dynamic completion for `shell`.
+///
+/// Unlike the static `clap_complete` scripts, this stub doesn't enumerate anything
+/// itself - it just shells out to `q _complete` on every Tab and prints whatever
+/// candidates come back, so updates to dynamic state never require regenerating or
+/// resourcing anything.
+pub fn shell_stub(bin_name: &str, shell: DynamicShell) -> String {
+    match shell {
+        // `-C` registers `{bin_name} _complete_bash` itself as the completer, with
+        // no wrapper function: bash invokes it with `$COMP_LINE`/`$COMP_POINT` in
+        // its environment and collects whatever it prints to
+        // stdout, one candidate per line, straight into `COMPREPLY`. The
+        // `_complete_bash` entry point (outside this module) reads those env vars,
+        // tokenizes `$COMP_LINE` into `args`/`cursor` the same way the zsh/fish
+        // stubs pass them explicitly, and calls `complete_dynamic`.
+        DynamicShell::Bash => format!("complete -o nospace -o bashdefault -C \"{bin_name} _complete_bash\" {bin_name}\n"),
+        DynamicShell::Zsh => format!(
+            "#compdef {bin_name}\n\n\
+             _{bin_name}_dynamic_complete() {{\n  \
+             local -a lines values descs\n  \
+             lines=(${{(f)\"$(\"{bin_name}\" _complete -- \"${{words[@]}}\" \"$((CURRENT - 1))\" --with-descriptions)\"}})\n  \
+             values=(${{lines%%$'\\t'*}})\n  \
+             descs=(${{${{lines/$'\\t'/ -- }}% -- }})\n  \
+             compadd -d descs -a values\n\
+             }}\ncompdef _{bin_name}_dynamic_complete {bin_name}\n"
+        ),
+        DynamicShell::Fish => format!(
+            "complete -c {bin_name} -f -a \"({bin_name} _complete -- (commandline -op) (commandline -t) (count (commandline -opc)) --with-descriptions)\"\n"
+        ),
+    }
+}
@@ -0,0 +1,242 @@
+//! Completion formats beyond what `clap_complete` ships natively: an external
+//! completer script for Nushell, a `clap_complete`-backed Elvish script (wired up
+//! here rather than in [`super::install`] since it shares nothing with the
+//! directory-based shells), and a Carapace-compatible spec export that lets any
+//! cross-shell completion engine consume one generated artifact instead of a
+//! shell-specific script.
+
+use clap::Command;
+use serde::Serialize;
+use serde_json::Value;
+
+/// A completion format this module knows how to generate that isn't one of the
+/// `clap_complete`-native shells `q completions install` already handles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtendedFormat {
+    Nushell,
+    Elvish,
+    CarapaceJson,
+    CarapaceYaml,
+}
+
+/// Generates the completion artifact for `format` from `cmd`.
+pub fn generate_extended(cmd: &mut Command, bin_name: &str, format: ExtendedFormat) -> String {
+    match format {
+        ExtendedFormat::Nushell => nushell_completer(bin_name),
+        ExtendedFormat::Elvish => elvish_script(cmd, bin_name),
+        ExtendedFormat::CarapaceJson => {
+            serde_json::to_string_pretty(&command_spec(cmd)).expect("CommandSpec always serializes")
+        },
+        ExtendedFormat::CarapaceYaml => {
+            let value = serde_json::to_value(command_spec(cmd)).expect("CommandSpec always serializes");
+            json_value_to_yaml(&value, 0)
+        },
+    }
+}
+
+/// Nushell has no native `clap_complete` target; instead it supports registering an
+/// "external completer" closure that gets the current command line and returns a
+/// table of `{value, description}` records. We shell out to the same `q _complete`
+/// dynamic engine (see [`super::dynamic`]) and reshape its TSV output into records.
+fn nushell_completer(bin_name: &str) -> String {
+    format!(
+        "let {bin_name}_completer = {{|spans|\n  \
+         ^{bin_name} _complete -- ...$spans (($spans | length) - 1) --with-descriptions\n  \
+         | lines\n  \
+         | each {{|line| $line | split column \"\\t\" value description }}\n  \
+         | flatten\n\
+         }}\n\n$env.config = ($env.config | upsert completions.external {{\n  \
+         enable: true\n  \
+         completer: ${bin_name}_completer\n\
+         }})\n"
+    )
+}
+
+/// Elvish is one of `clap_complete`'s native `Shell` targets, but `q completions
+/// install` never routes to it (see [`super::install::install_target`]), so we
+/// generate it here for `q completions elvish` instead.
+fn elvish_script(cmd: &mut Command, bin_name: &str) -> String {
+    let mut buf = std::io::Cursor::new(Vec::new());
+    clap_complete::generate(clap_complete::Shell::Elvish, cmd, bin_name, &mut buf);
+    String::from_utf8(buf.into_inner()).expect("clap_complete emits valid UTF-8")
+}
+
+/// A structured description of one command's flags, positional arguments, and
+/// subcommands, independent of any single shell's script syntax. This is what gets
+/// exported as the Carapace spec so a cross-shell completion engine can consume a
+/// single artifact instead of a shell-specific script.
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandSpec {
+    pub name: String,
+    pub about: Option<String>,
+    pub flags: Vec<FlagSpec>,
+    pub args: Vec<ArgSpec>,
+    pub subcommands: Vec<CommandSpec>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FlagSpec {
+    pub long: Option<String>,
+    pub short: Option<char>,
+    pub description: Option<String>,
+    pub takes_value: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArgSpec {
+    pub name: String,
+    pub value_hint: String,
+}
+
+/// Walks `cmd`'s full subcommand tree into a [`CommandSpec`].
+pub fn command_spec(cmd: &Command) -> CommandSpec {
+    let mut flags = Vec::new();
+    let mut args = Vec::new();
+
+    for arg in cmd.get_arguments() {
+        if arg.is_positional() {
+            args.push(ArgSpec {
+                name: arg.get_id().to_string(),
+                value_hint: format!("{:?}", arg.get_value_hint()),
+            });
+        } else {
+            flags.push(FlagSpec {
+                long: arg.get_long().map(str::to_string),
+                short: arg.get_short(),
+                description: arg.get_help().map(|h| h.to_string()),
+                takes_value: arg.get_num_args().is_some_and(|n| n.max_values() > 0),
+            });
+        }
+    }
+
+    let mut subcommands: Vec<CommandSpec> = cmd.get_subcommands().map(command_spec).collect();
+    subcommands.sort_by(|a, b| a.name.cmp(&b.name));
+
+    CommandSpec {
+        name: cmd.get_name().to_string(),
+        about: cmd.get_about().map(|a| a.to_string()),
+        flags,
+        args,
+        subcommands,
+    }
+}
+
+/// Renders a `serde_json::Value` as YAML.
+///
+/// The Carapace spec schema is simple enough (nested maps, lists, scalars) that
+/// pulling in a full YAML serializer isn't worth it: we already build the `Value`
+/// for the JSON export, so we reuse it and just walk it with YAML's indentation
+/// rules instead.
+fn json_value_to_yaml(value: &Value, indent: usize) -> String {
+    let pad = "  ".repeat(indent);
+    match value {
+        Value::Null => "null\n".to_string(),
+        Value::Bool(b) => format!("{b}\n"),
+        Value::Number(n) => format!("{n}\n"),
+        Value::String(s) => format!("{}\n", yaml_scalar(s)),
+        Value::Array(items) => {
+            if items.is_empty() {
+                return "[]\n".to_string();
+            }
+            let mut out = String::new();
+            for item in items {
+                match item {
+                    Value::Object(map) if !map.is_empty() => {
+                        out.push_str(&pad);
+                        out.push_str("-\n");
+                        out.push_str(&json_value_to_yaml(item, indent + 1));
+                    },
+                    _ => {
+                        out.push_str(&pad);
+                        out.push_str("- ");
+                        out.push_str(&json_value_to_yaml(item, indent + 1));
+                    },
+                }
+            }
+            out
+        },
+        Value::Object(map) => {
+            if map.is_empty() {
+                return "{}\n".to_string();
+            }
+            let mut out = String::new();
+            for (key, val) in map {
+                out.push_str(&pad);
+                out.push_str(key);
+                out.push(':');
+                match val {
+                    Value::Object(m) if !m.is_empty() => {
+                        out.push('\n');
+                        out.push_str(&json_value_to_yaml(val, indent + 1));
+                    },
+                    Value::Array(a) if !a.is_empty() => {
+                        out.push('\n');
+                        out.push_str(&json_value_to_yaml(val, indent));
+                    },
+                    _ => {
+                        out.push(' ');
+                        out.push_str(&json_value_to_yaml(val, indent + 1));
+                    },
+                }
+            }
+            out
+        },
+    }
+}
+
+fn yaml_scalar(s: &str) -> String {
+    if s.is_empty() || s.contains(['\n', ':', '#']) || s.trim() != s {
+        format!("{s:?}")
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Arg;
+
+    use super::*;
+
+    fn test_command() -> Command {
+        Command::new("q")
+            .about("Amazon Q CLI")
+            .arg(Arg::new("profile").long("profile").help("Profile to use"))
+            .subcommand(
+                Command::new("completions")
+                    .about("Generate shell completions")
+                    .subcommand(Command::new("install").arg(Arg::new("fuzzy").long("fuzzy").num_args(0))),
+            )
+    }
+
+    #[test]
+    fn carapace_json_round_trips_the_command_tree() {
+        let mut cmd = test_command();
+        let json = generate_extended(&mut cmd, "q", ExtendedFormat::CarapaceJson);
+        let parsed: Value = serde_json::from_str(&json).expect("emitted spec is valid JSON");
+
+        assert_eq!(parsed["name"], "q");
+        let flags = parsed["flags"].as_array().expect("flags is an array");
+        assert!(flags.iter().any(|f| f["long"] == "profile"));
+
+        let subcommands = parsed["subcommands"].as_array().expect("subcommands is an array");
+        let completions = subcommands
+            .iter()
+            .find(|s| s["name"] == "completions")
+            .expect("completions subcommand present");
+        let nested = completions["subcommands"].as_array().expect("nested subcommands is an array");
+        let install = nested.iter().find(|s| s["name"] == "install").expect("install subcommand present");
+        let install_flags = install["flags"].as_array().expect("install flags is an array");
+        assert!(install_flags.iter().any(|f| f["long"] == "fuzzy"));
+    }
+
+    #[test]
+    fn carapace_yaml_contains_every_subcommand_name() {
+        let mut cmd = test_command();
+        let yaml = generate_extended(&mut cmd, "q", ExtendedFormat::CarapaceYaml);
+
+        assert!(yaml.contains("completions"));
+        assert!(yaml.contains("install"));
+        assert!(yaml.contains("profile"));
+    }
+}
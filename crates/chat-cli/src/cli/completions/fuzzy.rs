@@ -0,0 +1,61 @@
+//! Optional fzf-backed fuzzy completion, layered on top of the dynamic completion
+//! engine in [`super::dynamic`].
+//!
+//! This only changes the shell stub: the candidate source is still `q _complete`.
+//! When the word being completed ends in the `**` trigger, the stub strips it,
+//! fetches candidates, and hands them to `fzf` for fuzzy, previewable selection
+//! instead of the shell's normal completion menu. If `fzf` isn't on `$PATH`, the
+//! stub silently falls back to the plain dynamic completion it would otherwise use.
+
+use super::dynamic::DynamicShell;
+
+/// The suffix that, when trailing the current word, switches a dynamic completion
+/// stub from normal completion to the fzf picker.
+pub const TRIGGER: &str = "**";
+
+/// Generates the fuzzy-aware completion stub for `shell`.
+///
+/// Falls back to [`super::dynamic::shell_stub`]'s behavior (plain dynamic
+/// completion, no fzf) both when the trigger isn't present on the current word and
+/// when `fzf` isn't installed, so enabling `--fuzzy` is never a regression.
+pub fn fuzzy_shell_stub(bin_name: &str, shell: DynamicShell) -> String {
+    match shell {
+        DynamicShell::Bash => format!(
+            "_{bin_name}_fuzzy_complete() {{\n  \
+             local cur=\"${{COMP_WORDS[COMP_CWORD]}}\"\n  \
+             if [[ \"$cur\" == *'{TRIGGER}' && -n \"$(command -v fzf)\" ]]; then\n    \
+             local stripped=\"${{cur%{TRIGGER}}}\"\n    \
+             COMP_WORDS[COMP_CWORD]=\"$stripped\"\n    \
+             local candidates\n    \
+             candidates=$(\"{bin_name}\" _complete -- \"${{COMP_WORDS[@]}}\" \"$COMP_CWORD\" --with-descriptions)\n    \
+             local chosen\n    \
+             chosen=$(echo \"$candidates\" | fzf --ansi --delimiter='\\t' --with-nth=1,2 --nth=1 | cut -f1)\n    \
+             COMPREPLY=(\"$chosen\")\n  \
+             else\n    \
+             COMPREPLY=($(\"{bin_name}\" _complete -- \"${{COMP_WORDS[@]}}\" \"$COMP_CWORD\"))\n  \
+             fi\n\
+             }}\ncomplete -o nospace -o bashdefault -F _{bin_name}_fuzzy_complete {bin_name}\n"
+        ),
+        DynamicShell::Zsh => format!(
+            "#compdef {bin_name}\n\n\
+             _{bin_name}_fuzzy_complete() {{\n  \
+             local cur=\"${{words[CURRENT]}}\"\n  \
+             if [[ \"$cur\" == *'{TRIGGER}' && -n \"$(command -v fzf)\" ]]; then\n    \
+             local stripped=\"${{cur%{TRIGGER}}}\"\n    \
+             words[CURRENT]=\"$stripped\"\n    \
+             local candidates chosen\n    \
+             candidates=$(\"{bin_name}\" _complete -- \"${{words[@]}}\" \"$((CURRENT - 1))\" --with-descriptions)\n    \
+             chosen=$(echo \"$candidates\" | fzf --ansi --delimiter='\\t' --with-nth=1,2 --nth=1 | cut -f1)\n    \
+             compadd -U -- \"$chosen\"\n  \
+             else\n    \
+             local -a plain\n    \
+             plain=(${{(f)\"$(\"{bin_name}\" _complete -- \"${{words[@]}}\" \"$((CURRENT - 1))\")\"}})\n    \
+             compadd -a plain\n  \
+             fi\n\
+             }}\ncompdef _{bin_name}_fuzzy_complete {bin_name}\n"
+        ),
+        // fzf's `**` trigger is a bash/zsh convention; fish has no equivalent, so
+        // fuzzy mode is a no-op there and we just emit the plain dynamic stub.
+        DynamicShell::Fish => super::dynamic::shell_stub(bin_name, shell),
+    }
+}
@@ -0,0 +1,213 @@
+//! Installing static completion scripts into each shell's standard completion
+//! directory (`q completions install` / `--uninstall`).
+
+use std::fs;
+use std::io::Cursor;
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use clap::Command;
+use clap_complete::Shell;
+use eyre::{
+    Result,
+    bail,
+    eyre,
+};
+
+/// Where an installed completion script lives for a given shell; `--uninstall` finds
+/// it again at the same path.
+struct InstallTarget {
+    /// Directory the script should be written into.
+    dir: PathBuf,
+    /// File name within `dir`.
+    file_name: &'static str,
+}
+
+impl InstallTarget {
+    fn path(&self) -> PathBuf {
+        self.dir.join(self.file_name)
+    }
+}
+
+/// Resolves the conventional per-shell completion directory for the current user.
+///
+/// These are the locations each shell searches by default; they intentionally match
+/// what a user would otherwise set up by hand following that shell's completion docs.
+fn install_target(shell: Shell) -> Result<InstallTarget> {
+    let home = dirs::home_dir().ok_or_else(|| eyre!("could not determine home directory"))?;
+
+    let target = match shell {
+        Shell::Bash => InstallTarget {
+            dir: home.join(".local/share/bash-completion/completions"),
+            file_name: "q",
+        },
+        Shell::Fish => InstallTarget {
+            dir: home.join(".config/fish/vendor_completions.d"),
+            file_name: "q.fish",
+        },
+        Shell::Zsh => InstallTarget {
+            dir: zsh_site_functions_dir(&home),
+            file_name: "_q",
+        },
+        Shell::PowerShell => InstallTarget {
+            dir: powershell_profile_dir(&home)?,
+            file_name: "Microsoft.PowerShell_profile.ps1",
+        },
+        other => bail!("completion install is not supported for {other}"),
+    };
+
+    Ok(target)
+}
+
+/// Picks a `zsh` `site-functions` directory to hold `_q`.
+///
+/// `zsh` has no single canonical completions directory the way `bash-completion` or
+/// fish's `vendor_completions.d` do, and `~/.zsh/site-functions` isn't on `$fpath` by
+/// default, so [`install`] prints a one-time instruction to add it the first time
+/// this directory is created (see [`warn_zsh_fpath`]).
+fn zsh_site_functions_dir(home: &Path) -> PathBuf {
+    home.join(".zsh/site-functions")
+}
+
+/// Printed once, the first time `~/.zsh/site-functions` is created, since nothing
+/// puts it on `$fpath` for us and `_q` silently never loads without it.
+fn warn_zsh_fpath(dir: &Path) {
+    eprintln!(
+        "note: completions were written to {} - add it to $fpath before `compinit` runs in your \
+         .zshrc, e.g.:\n  fpath=({} $fpath)",
+        dir.display(),
+        dir.display()
+    );
+}
+
+/// Directory holding `$PROFILE` for PowerShell Core (`pwsh`) on Linux/macOS, where
+/// `Microsoft.PowerShell_profile.ps1` is dot-sourced on every interactive shell
+/// startup. We append our marker block straight into that file rather than a
+/// standalone one, since PowerShell has no convention for auto-loaded completion
+/// scripts the way bash-completion or fish's `vendor_completions.d` do.
+fn powershell_profile_dir(home: &Path) -> Result<PathBuf> {
+    Ok(home.join(".config/powershell"))
+}
+
+/// Generates the completion script for `shell` from `cmd` and returns it as bytes.
+pub(super) fn generate_script(cmd: &mut Command, bin_name: &str, shell: Shell) -> Vec<u8> {
+    let mut buf = Cursor::new(Vec::new());
+    clap_complete::generate(shell, cmd, bin_name, &mut buf);
+    buf.into_inner()
+}
+
+/// Installs (or overrides the destination of) the completion script for `shell`.
+///
+/// Creates any missing parent directories and overwrites an existing file in place,
+/// so running this repeatedly is a no-op once the script is up to date. Returns the
+/// path the script was written to.
+pub fn install(cmd: &mut Command, bin_name: &str, shell: Shell, output_dir: Option<PathBuf>) -> Result<PathBuf> {
+    let mut target = install_target(shell)?;
+    if let Some(dir) = output_dir {
+        target.dir = dir;
+    }
+    let path = target.path();
+
+    let dir_is_new = !target.dir.exists();
+    fs::create_dir_all(&target.dir)?;
+    if shell == Shell::Zsh && dir_is_new {
+        warn_zsh_fpath(&target.dir);
+    }
+    let script = generate_script(cmd, bin_name, shell);
+
+    if shell == Shell::PowerShell {
+        append_powershell_profile(&path, &script)?;
+    } else {
+        fs::write(&path, script)?;
+    }
+
+    Ok(path)
+}
+
+/// PowerShell completions are sourced by appending a marked block to `$PROFILE`
+/// itself rather than dropping a standalone file nothing loads, so `install`/
+/// `uninstall` must find and replace only the block we previously inserted.
+const POWERSHELL_MARKER_BEGIN: &str = "# >>> q completions >>>";
+const POWERSHELL_MARKER_END: &str = "# <<< q completions <<<";
+
+fn append_powershell_profile(path: &Path, script: &[u8]) -> Result<()> {
+    let script = std::str::from_utf8(script)?;
+    let existing = fs::read_to_string(path).unwrap_or_default();
+    let without_block = strip_powershell_block(&existing);
+
+    let mut updated = without_block;
+    if !updated.is_empty() && !updated.ends_with('\n') {
+        updated.push('\n');
+    }
+    updated.push_str(POWERSHELL_MARKER_BEGIN);
+    updated.push('\n');
+    updated.push_str(script.trim_end());
+    updated.push('\n');
+    updated.push_str(POWERSHELL_MARKER_END);
+    updated.push('\n');
+
+    fs::write(path, updated)?;
+    Ok(())
+}
+
+fn strip_powershell_block(contents: &str) -> String {
+    let Some(start) = contents.find(POWERSHELL_MARKER_BEGIN) else {
+        return contents.to_string();
+    };
+    let Some(end) = contents.find(POWERSHELL_MARKER_END) else {
+        return contents.to_string();
+    };
+    let mut end = end + POWERSHELL_MARKER_END.len();
+    if contents[end..].starts_with('\n') {
+        end += 1;
+    }
+
+    let mut result = contents[..start].to_string();
+    result.push_str(&contents[end..]);
+    result
+}
+
+/// Removes a previously installed completion script for `shell`, if present.
+///
+/// For `bash`/`fish`/`zsh` this deletes the installed file outright. For
+/// PowerShell it strips the marked block out of the profile instead of deleting the
+/// whole profile file.
+pub fn uninstall(shell: Shell, output_dir: Option<PathBuf>) -> Result<PathBuf> {
+    let mut target = install_target(shell)?;
+    if let Some(dir) = output_dir {
+        target.dir = dir;
+    }
+    let path = target.path();
+
+    if shell == Shell::PowerShell {
+        if path.exists() {
+            let existing = fs::read_to_string(&path)?;
+            let stripped = strip_powershell_block(&existing);
+            fs::write(&path, stripped)?;
+        }
+        return Ok(path);
+    }
+
+    if path.exists() {
+        fs::remove_file(&path)?;
+    }
+
+    Ok(path)
+}
+
+/// Best-effort detection of the user's active shell from `$SHELL`, used as the
+/// default for `q completions install` when `--shell` is not given.
+pub fn detect_shell() -> Option<Shell> {
+    let shell_path = std::env::var_os("SHELL")?;
+    let shell_name = Path::new(&shell_path).file_name()?.to_str()?;
+
+    match shell_name {
+        "bash" => Some(Shell::Bash),
+        "zsh" => Some(Shell::Zsh),
+        "fish" => Some(Shell::Fish),
+        "pwsh" | "powershell" => Some(Shell::PowerShell),
+        _ => None,
+    }
+}
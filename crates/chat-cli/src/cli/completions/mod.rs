@@ -0,0 +1,46 @@
+//! Shell completions support (fish, zsh, bash, etc).
+//!
+//! Static script generation is implemented in `cli/mod.rs` via the `Completions`
+//! RootSubcommand variant, which prints a `clap_complete`-generated script to stdout
+//! for the user to `source`. This module owns everything beyond that baseline:
+//!
+//! - [`install`]: writing/removing those scripts from each shell's standard
+//!   completion directory (`q completions install` / `--uninstall`).
+//! - [`dynamic`]: the `q _complete` engine backing live, non-static completions
+//!   (profiles, config keys, paths) via a per-shell stub.
+//! - [`fuzzy`]: an opt-in fzf picker layered on top of the dynamic stub, triggered by
+//!   a trailing `**` on the word being completed.
+//! - [`cache`]: caches generated scripts keyed on CLI version and command-tree
+//!   digest, for near-instant `--cached` regeneration.
+//! - [`formats`]: generators `clap_complete` doesn't ship natively - Nushell,
+//!   Elvish, and a Carapace-compatible spec export.
+//! - (future work) rich text previews.
+
+mod cache;
+mod dynamic;
+mod formats;
+mod fuzzy;
+mod install;
+
+pub use cache::{
+    clear_cache,
+    get_or_generate,
+};
+pub use dynamic::{
+    Completion,
+    DynamicShell,
+    complete_dynamic,
+    format_candidates,
+    shell_stub,
+};
+pub use formats::{
+    CommandSpec,
+    ExtendedFormat,
+    generate_extended,
+};
+pub use fuzzy::fuzzy_shell_stub;
+pub use install::{
+    detect_shell,
+    install,
+    uninstall,
+};